@@ -9,16 +9,265 @@ use std::os::unix::ffi::{OsStrExt, OsStringExt};
 #[cfg(windows)]
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
 
-use rustc_target::abi::LayoutOf;
+use rustc_target::abi::{LayoutOf, Size};
 
 use crate::*;
 
+/// Encoding and decoding between `u16` code units (what Windows-style APIs hand us) and WTF-8
+/// bytes (what a Unix-style `OsStr` can hold verbatim). WTF-8 is a strict superset of UTF-8
+/// that can additionally represent unpaired surrogates `U+D800..=U+DFFF`, encoded using the
+/// very same algorithm UTF-8 would use if they were valid scalar values (e.g. `U+D800` becomes
+/// `ED A0 80`). Going through WTF-8 instead of `String`/`str::from_utf8` lets us shuttle
+/// arbitrary, possibly ill-formed, wide strings between hosts without ever losing data.
+/// See <https://simonsapin.github.io/wtf-8/> for the full picture.
+mod wtf8 {
+    /// `decode_wide` surrogate-escapes a host byte that is not part of any well-formed UTF-8
+    /// sequence (e.g. a lone continuation byte, an overlong encoding, or a truncated
+    /// multi-byte sequence) into a code unit drawn from this sub-range of the *high*
+    /// surrogates. Byte `b` (always `0x80..=0xFF`, since `0x00..=0x7F` is already valid UTF-8
+    /// on its own) escapes to `SURROGATE_ESCAPE_BASE + b`, landing in
+    /// `SURROGATE_ESCAPE_LOW..=SURROGATE_ESCAPE_HIGH`. This sub-range is deliberately carved
+    /// out of the *high* surrogates rather than the low ones: `encode_wide` only ever looks
+    /// for a combinable low surrogate *after* a high surrogate, so anchoring the escape here
+    /// means a genuine unpaired high surrogate immediately followed by an escaped byte can
+    /// never be misread as the start of a surrogate pair.
+    const SURROGATE_ESCAPE_BASE: u16 = 0xD800;
+    const SURROGATE_ESCAPE_LOW: u16 = SURROGATE_ESCAPE_BASE + 0x80;
+    const SURROGATE_ESCAPE_HIGH: u16 = SURROGATE_ESCAPE_BASE + 0xFF;
+
+    /// Encode a sequence of UTF-16 code units, including unpaired surrogates and
+    /// surrogate-escaped raw bytes (see `SURROGATE_ESCAPE_BASE`), as WTF-8 bytes.
+    pub fn encode_wide(units: &[u16]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(units.len());
+        let mut units = units.iter().copied().peekable();
+        while let Some(unit) = units.next() {
+            match unit {
+                SURROGATE_ESCAPE_LOW..=SURROGATE_ESCAPE_HIGH =>
+                    bytes.push((unit - SURROGATE_ESCAPE_BASE) as u8),
+                0xD800..=0xDBFF => match units.peek() {
+                    Some(&low) if (0xDC00..=0xDFFF).contains(&low) => {
+                        units.next();
+                        let scalar =
+                            0x10000 + (u32::from(unit) - 0xD800) * 0x400 + (u32::from(low) - 0xDC00);
+                        push_code_point(&mut bytes, scalar);
+                    }
+                    _ => push_code_point(&mut bytes, u32::from(unit)),
+                },
+                _ => push_code_point(&mut bytes, u32::from(unit)),
+            }
+        }
+        bytes
+    }
+
+    /// Push a single Unicode scalar value, or an unpaired surrogate half, the same way UTF-8
+    /// would encode that code point if it were a valid scalar value.
+    fn push_code_point(bytes: &mut Vec<u8>, c: u32) {
+        match c {
+            0..=0x7F => bytes.push(c as u8),
+            0x80..=0x7FF => bytes.extend([0xC0 | (c >> 6) as u8, 0x80 | (c & 0x3F) as u8]),
+            0x800..=0xFFFF => bytes.extend([
+                0xE0 | (c >> 12) as u8,
+                0x80 | ((c >> 6) & 0x3F) as u8,
+                0x80 | (c & 0x3F) as u8,
+            ]),
+            _ => bytes.extend([
+                0xF0 | (c >> 18) as u8,
+                0x80 | ((c >> 12) & 0x3F) as u8,
+                0x80 | ((c >> 6) & 0x3F) as u8,
+                0x80 | (c & 0x3F) as u8,
+            ]),
+        }
+    }
+
+    /// Decode WTF-8 bytes back into UTF-16 code units, reconstructing surrogate pairs for
+    /// supplementary-plane scalars and leaving unpaired surrogates as lone code units. Bytes
+    /// that do not form a well-formed (WTF-)8 sequence -- a lone continuation byte, an
+    /// overlong encoding, a 4-byte sequence past the Unicode maximum, a truncated sequence,
+    /// or arbitrary non-UTF-8 host bytes that never went through `encode_wide` -- are
+    /// surrogate-escaped (see `SURROGATE_ESCAPE_BASE`) one byte at a time rather than
+    /// panicking or being dropped, so this never fails and `encode_wide` can recover the
+    /// exact original bytes.
+    pub fn decode_wide(bytes: &[u8]) -> Vec<u16> {
+        fn is_cont(b: u8) -> bool {
+            b & 0xC0 == 0x80
+        }
+        fn cont(b: u8) -> u32 {
+            u32::from(b & 0x3F)
+        }
+
+        let mut units = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let b0 = bytes[i];
+            // Each multi-byte arm rejects encodings that are shorter than the minimum
+            // required for the decoded scalar ("overlong" forms) and, for 4-byte sequences,
+            // scalars past the Unicode maximum; such ill-formed sequences fall through to
+            // the single-byte surrogate-escape below just like any other invalid lead byte.
+            let decoded = if b0 < 0x80 {
+                Some((u32::from(b0), 1))
+            } else if b0 & 0xE0 == 0xC0 && i + 1 < bytes.len() && is_cont(bytes[i + 1]) {
+                let c = (u32::from(b0 & 0x1F) << 6) | cont(bytes[i + 1]);
+                (c >= 0x80).then(|| (c, 2))
+            } else if b0 & 0xF0 == 0xE0
+                && i + 2 < bytes.len()
+                && is_cont(bytes[i + 1])
+                && is_cont(bytes[i + 2])
+            {
+                let c =
+                    (u32::from(b0 & 0x0F) << 12) | (cont(bytes[i + 1]) << 6) | cont(bytes[i + 2]);
+                // `U+D800..=U+DFFF` is deliberately accepted here: encoding unpaired
+                // surrogates via this very same 3-byte form is the whole point of WTF-8.
+                (c >= 0x800).then(|| (c, 3))
+            } else if b0 & 0xF8 == 0xF0
+                && i + 3 < bytes.len()
+                && is_cont(bytes[i + 1])
+                && is_cont(bytes[i + 2])
+                && is_cont(bytes[i + 3])
+            {
+                let c = (u32::from(b0 & 0x07) << 18)
+                    | (cont(bytes[i + 1]) << 12)
+                    | (cont(bytes[i + 2]) << 6)
+                    | cont(bytes[i + 3]);
+                (c >= 0x10000 && c <= 0x10FFFF).then(|| (c, 4))
+            } else {
+                None
+            };
+            let (c, len) = decoded.unwrap_or((u32::from(SURROGATE_ESCAPE_BASE) + u32::from(b0), 1));
+            if c < 0x10000 {
+                units.push(c as u16);
+            } else {
+                let c = c - 0x10000;
+                units.push(0xD800 + (c >> 10) as u16);
+                units.push(0xDC00 + (c & 0x3FF) as u16);
+            }
+            i += len;
+        }
+        units
+    }
+}
+
+/// Convert a `u16` buffer (which may contain unpaired surrogates) into an `OsString`,
+/// preserving it losslessly regardless of host.
+#[cfg(windows)]
+fn u16vec_to_osstring<'tcx>(u16_vec: Vec<u16>) -> InterpResult<'tcx, OsString> {
+    Ok(OsString::from_wide(&u16_vec[..]))
+}
+#[cfg(unix)]
+fn u16vec_to_osstring<'tcx>(u16_vec: Vec<u16>) -> InterpResult<'tcx, OsString> {
+    // On Unix, an `OsStr` is just an arbitrary byte sequence, so storing the WTF-8 encoding
+    // of `u16_vec` directly round-trips losslessly, unlike going through `String::from_utf16`.
+    let bytes = wtf8::encode_wide(&u16_vec);
+    Ok(OsString::from_vec(bytes))
+}
+#[cfg(not(any(unix, windows)))]
+fn u16vec_to_osstring<'tcx>(u16_vec: Vec<u16>) -> InterpResult<'tcx, OsString> {
+    // Hosts with neither `OsStrExt` nor `OsStringExt` have no safe way to build an `OsString`
+    // from an arbitrary byte or `u16` sequence, so we fall back to requiring valid UTF-16.
+    let s = String::from_utf16(&u16_vec[..])
+        .map_err(|_| err_unsup_format!("{:?} is not a valid utf-16 string", u16_vec))?;
+    Ok(s.into())
+}
+
+/// Convert an `OsStr` into a `u16` buffer (which may contain unpaired surrogates),
+/// preserving it losslessly regardless of host.
+#[cfg(windows)]
+fn os_str_to_u16vec<'tcx>(os_str: &OsStr) -> InterpResult<'tcx, Vec<u16>> {
+    Ok(os_str.encode_wide().collect())
+}
+#[cfg(unix)]
+fn os_str_to_u16vec<'tcx>(os_str: &OsStr) -> InterpResult<'tcx, Vec<u16>> {
+    // The bytes of a Unix `OsStr` are WTF-8 whenever they originated from `u16vec_to_osstring`
+    // above; decoding them back to `u16`s recovers the original sequence exactly, including
+    // any unpaired surrogates.
+    Ok(wtf8::decode_wide(os_str.as_bytes()))
+}
+#[cfg(not(any(unix, windows)))]
+fn os_str_to_u16vec<'tcx>(os_str: &OsStr) -> InterpResult<'tcx, Vec<u16>> {
+    os_str
+        .to_str()
+        .map(|s| s.encode_utf16().collect())
+        .ok_or_else(|| err_unsup_format!("{:?} is not a valid utf-8 string", os_str).into())
+}
+
+/// Build the flat `u16` layout `write_os_str_block_to_wide_str` writes and
+/// `read_os_str_block_from_wide_str` reads back: each string's code units followed by its own
+/// `0x0000` terminator, with one more `0x0000` ending the whole block. Returns the block
+/// together with the combined length of all strings, not counting any of the terminators.
+fn encode_wide_str_block<'tcx>(
+    os_strings: impl IntoIterator<Item = impl AsRef<OsStr>>,
+) -> InterpResult<'tcx, (Vec<u16>, u64)> {
+    let u16_vecs = os_strings
+        .into_iter()
+        .map(|os_str| os_str_to_u16vec(os_str.as_ref()))
+        .collect::<InterpResult<'tcx, Vec<_>>>()?;
+    let string_length: u64 = u16_vecs.iter().map(|v| u64::try_from(v.len()).unwrap()).sum();
+    let mut block = Vec::new();
+    for u16_vec in u16_vecs {
+        block.extend(u16_vec);
+        block.push(0x0000);
+    }
+    block.push(0x0000);
+    Ok((block, string_length))
+}
+
+/// Whether a destination of `size` code units is large enough to hold `block` (as produced by
+/// `encode_wide_str_block`).
+fn wide_str_block_fits(block: &[u16], size: u64) -> bool {
+    size >= u64::try_from(block.len()).unwrap()
+}
+
+/// Split a flat block laid out by `encode_wide_str_block` back into its component strings.
+fn decode_wide_str_block<'tcx>(block: &[u16]) -> InterpResult<'tcx, Vec<OsString>> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    while start < block.len() {
+        // Every string in a well-formed block, including the block's own trailing one, is
+        // itself `0x0000`-terminated, so this always finds a terminator.
+        let len = block[start..].iter().position(|&u| u == 0x0000).unwrap();
+        if len == 0 {
+            // The extra trailing 0x0000 that terminates the whole block.
+            break;
+        }
+        result.push(u16vec_to_osstring(block[start..start + len].to_vec())?);
+        start += len + 1;
+    }
+    Ok(result)
+}
+
 /// Represent how path separator conversion should be done.
 enum Pathconversion {
     HostToTarget,
     TargetToHost,
 }
 
+/// Which host directory each Windows drive letter is rooted at, when translating a path
+/// between a Windows target and a non-Windows host. A fuller implementation would let this be
+/// populated from a command-line flag; for now `C:` is the only configured entry.
+static DRIVE_TABLE: &[(u8, &str)] = &[(b'C', "/")];
+
+/// Look up the host root a Windows drive letter is mounted at, if any.
+fn host_root_for_drive(letter: u8) -> Option<&'static str> {
+    DRIVE_TABLE.iter().find(|&&(l, _)| l == letter).map(|&(_, root)| root)
+}
+
+/// Find the drive letter, if any, whose configured host root `bytes` lives under.
+fn drive_for_host_path(bytes: &[u8]) -> Option<(u8, &'static str)> {
+    DRIVE_TABLE.iter().copied().find(|&(_, root)| bytes.starts_with(root.as_bytes()))
+}
+
+/// Strip a Windows verbatim-path prefix (`\\?\`) and a drive letter (`C:`) off the start of a
+/// byte-oriented path, returning the (uppercased) drive letter, if any, and the remaining
+/// bytes. Only the leading ASCII bytes are inspected, so this is safe to call on WTF-8 or
+/// otherwise non-UTF-8 byte sequences: nothing past the prefix is interpreted.
+fn split_windows_drive_prefix(bytes: &[u8]) -> (Option<u8>, &[u8]) {
+    let bytes = bytes.strip_prefix(br"\\?\").unwrap_or(bytes);
+    match bytes {
+        [letter @ (b'a'..=b'z' | b'A'..=b'Z'), b':', rest @ ..] =>
+            (Some(letter.to_ascii_uppercase()), rest),
+        _ => (None, bytes),
+    }
+}
+
 /// Perform path separator conversion if needed.
 fn convert_path_separator<'a>(
     os_str: Cow<'a, OsStr>,
@@ -43,16 +292,60 @@ fn convert_path_separator<'a>(
     };
     #[cfg(unix)]
     return if target_os == "windows" {
-        // Windows target, Unix host.
+        // Windows target, Unix host. Besides swapping separators we also need to understand
+        // path *structure*: a target path like `C:\foo\bar` or a verbatim `\\?\C:\foo` is not
+        // a meaningful host path as-is, and a host path needs a drive prefix reconstructed
+        // before it is handed back to the guest.
         let (from, to) = match direction {
             Pathconversion::HostToTarget => ('/', '\\'),
             Pathconversion::TargetToHost => ('\\', '/'),
         };
-        let converted = os_str
-            .as_bytes()
-            .iter()
-            .map(|&wchar| if wchar == from as u8 { to as u8 } else { wchar })
-            .collect::<Vec<_>>();
+        let bytes = os_str.as_bytes();
+        let mut converted = match direction {
+            Pathconversion::TargetToHost => {
+                // Strip `\\?\` and a `C:` drive prefix, if present, and replace the latter
+                // with wherever that drive is mounted on the host, joined by exactly one
+                // separator (the prefix and `rest` each carry at most their own, not both).
+                // A path with no drive prefix (e.g. a driveless-absolute `\foo\bar`) is left
+                // as-is: its leading separator is part of the path, not a `C:` artifact, and
+                // must not be stripped.
+                let (drive, rest) = split_windows_drive_prefix(bytes);
+                let rest = if drive.is_some() {
+                    rest.strip_prefix(b"\\").or_else(|| rest.strip_prefix(b"/")).unwrap_or(rest)
+                } else {
+                    rest
+                };
+                let mut out = drive.and_then(host_root_for_drive).unwrap_or("").as_bytes().to_vec();
+                if !out.is_empty() && !out.ends_with(b"/") {
+                    out.push(b'/');
+                }
+                out.extend_from_slice(rest);
+                out
+            }
+            Pathconversion::HostToTarget => {
+                // If the host path lives under a mounted drive's root, reconstruct that
+                // drive's prefix so the guest sees a valid Windows path, inserting a
+                // separator after the drive letter if the stripped remainder didn't carry
+                // one of its own.
+                match drive_for_host_path(bytes) {
+                    Some((letter, root)) => {
+                        let rest = &bytes[root.len()..];
+                        let mut out = vec![letter, b':'];
+                        if !rest.is_empty() && !rest.starts_with(b"/") && !rest.starts_with(b"\\") {
+                            out.push(b'\\');
+                        }
+                        out.extend_from_slice(rest);
+                        out
+                    }
+                    None => bytes.to_vec(),
+                }
+            }
+        };
+        for byte in &mut converted {
+            if *byte == from as u8 {
+                *byte = to as u8;
+            }
+        }
         Cow::Owned(OsString::from_vec(converted))
     } else {
         // Unix-on-Unix, all is fine.
@@ -64,20 +357,31 @@ impl<'mir, 'tcx: 'mir> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mi
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
     /// Helper function to read an OsString from a null-terminated sequence of bytes, which is what
     /// the Unix APIs usually handle.
-    fn read_os_str_from_c_str<'a>(&'a self, scalar: Scalar<Tag>) -> InterpResult<'tcx, &'a OsStr>
+    fn read_os_str_from_c_str<'a>(
+        &'a self,
+        scalar: Scalar<Tag>,
+    ) -> InterpResult<'tcx, Cow<'a, OsStr>>
     where
         'tcx: 'a,
         'mir: 'a,
     {
         #[cfg(unix)]
-        fn bytes_to_os_str<'tcx, 'a>(bytes: &'a [u8]) -> InterpResult<'tcx, &'a OsStr> {
-            Ok(OsStr::from_bytes(bytes))
+        fn bytes_to_os_str<'tcx, 'a>(bytes: &'a [u8]) -> InterpResult<'tcx, Cow<'a, OsStr>> {
+            Ok(Cow::Borrowed(OsStr::from_bytes(bytes)))
+        }
+        #[cfg(windows)]
+        fn bytes_to_os_str<'tcx, 'a>(bytes: &'a [u8]) -> InterpResult<'tcx, Cow<'a, OsStr>> {
+            // Interpret `bytes` as WTF-8 so an arbitrary Unix-style byte string (e.g. a
+            // non-UTF-8 filename) round-trips losslessly onto a Windows host instead of
+            // erroring out on the first invalid byte.
+            let u16_vec = wtf8::decode_wide(bytes);
+            Ok(Cow::Owned(OsString::from_wide(&u16_vec)))
         }
-        #[cfg(not(unix))]
-        fn bytes_to_os_str<'tcx, 'a>(bytes: &'a [u8]) -> InterpResult<'tcx, &'a OsStr> {
+        #[cfg(not(any(unix, windows)))]
+        fn bytes_to_os_str<'tcx, 'a>(bytes: &'a [u8]) -> InterpResult<'tcx, Cow<'a, OsStr>> {
             let s = std::str::from_utf8(bytes)
                 .map_err(|_| err_unsup_format!("{:?} is not a valid utf-8 string", bytes))?;
-            Ok(OsStr::new(s))
+            Ok(Cow::Borrowed(OsStr::new(s)))
         }
 
         let this = self.eval_context_ref();
@@ -92,21 +396,36 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         'tcx: 'a,
         'mir: 'a,
     {
-        #[cfg(windows)]
-        pub fn u16vec_to_osstring<'tcx, 'a>(u16_vec: Vec<u16>) -> InterpResult<'tcx, OsString> {
-            Ok(OsString::from_wide(&u16_vec[..]))
-        }
-        #[cfg(not(windows))]
-        pub fn u16vec_to_osstring<'tcx, 'a>(u16_vec: Vec<u16>) -> InterpResult<'tcx, OsString> {
-            let s = String::from_utf16(&u16_vec[..])
-                .map_err(|_| err_unsup_format!("{:?} is not a valid utf-16 string", u16_vec))?;
-            Ok(s.into())
-        }
-
         let u16_vec = self.eval_context_ref().memory.read_wide_str(scalar)?;
         u16vec_to_osstring(u16_vec)
     }
 
+    /// Helper function to read a block of 0x0000-terminated sequences of `u16`, terminated by
+    /// an additional 0x0000, which is the layout e.g. `GetEnvironmentStringsW`, multi-path
+    /// `SHFileOperationW` inputs, and `REG_MULTI_SZ` registry values use.
+    fn read_os_str_block_from_wide_str(
+        &self,
+        mut scalar: Scalar<Tag>,
+    ) -> InterpResult<'tcx, Vec<OsString>> {
+        let this = self.eval_context_ref();
+        let mut block = Vec::new();
+        loop {
+            let u16_vec = this.memory.read_wide_str(scalar)?;
+            if u16_vec.is_empty() {
+                // The extra trailing 0x0000 that terminates the whole block.
+                block.push(0x0000);
+                break;
+            }
+            // Skip over this string's contents and its own 0x0000 terminator to reach the
+            // next string in the block.
+            let skip = Size::from_bytes(u64::try_from(u16_vec.len()).unwrap().checked_add(1).unwrap() * 2);
+            block.extend(u16_vec);
+            block.push(0x0000);
+            scalar = scalar.ptr_offset(skip, this)?;
+        }
+        decode_wide_str_block(&block)
+    }
+
     /// Helper function to write an OsStr as a null-terminated sequence of bytes, which is what
     /// the Unix APIs usually handle. This function returns `Ok((false, length))` without trying
     /// to write if `size` is not large enough to fit the contents of `os_string` plus a null
@@ -119,17 +438,25 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         size: u64,
     ) -> InterpResult<'tcx, (bool, u64)> {
         #[cfg(unix)]
-        fn os_str_to_bytes<'tcx, 'a>(os_str: &'a OsStr) -> InterpResult<'tcx, &'a [u8]> {
-            Ok(os_str.as_bytes())
+        fn os_str_to_bytes<'tcx, 'a>(os_str: &'a OsStr) -> InterpResult<'tcx, Cow<'a, [u8]>> {
+            Ok(Cow::Borrowed(os_str.as_bytes()))
         }
-        #[cfg(not(unix))]
-        fn os_str_to_bytes<'tcx, 'a>(os_str: &'a OsStr) -> InterpResult<'tcx, &'a [u8]> {
+        #[cfg(windows)]
+        fn os_str_to_bytes<'tcx, 'a>(os_str: &'a OsStr) -> InterpResult<'tcx, Cow<'a, [u8]>> {
+            // Encode the host `OsStr` (an arbitrary, possibly ill-formed, `u16` sequence) as
+            // WTF-8 so it can be written out as a null-terminated byte string without losing
+            // any unpaired surrogates.
+            let u16_vec: Vec<u16> = os_str.encode_wide().collect();
+            Ok(Cow::Owned(wtf8::encode_wide(&u16_vec)))
+        }
+        #[cfg(not(any(unix, windows)))]
+        fn os_str_to_bytes<'tcx, 'a>(os_str: &'a OsStr) -> InterpResult<'tcx, Cow<'a, [u8]>> {
             // On non-unix platforms the best we can do to transform bytes from/to OS strings is to do the
             // intermediate transformation into strings. Which invalidates non-utf8 paths that are actually
             // valid.
             os_str
                 .to_str()
-                .map(|s| s.as_bytes())
+                .map(|s| Cow::Borrowed(s.as_bytes()))
                 .ok_or_else(|| err_unsup_format!("{:?} is not a valid utf-8 string", os_str).into())
         }
 
@@ -157,21 +484,6 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         scalar: Scalar<Tag>,
         size: u64,
     ) -> InterpResult<'tcx, (bool, u64)> {
-        #[cfg(windows)]
-        fn os_str_to_u16vec<'tcx>(os_str: &OsStr) -> InterpResult<'tcx, Vec<u16>> {
-            Ok(os_str.encode_wide().collect())
-        }
-        #[cfg(not(windows))]
-        fn os_str_to_u16vec<'tcx>(os_str: &OsStr) -> InterpResult<'tcx, Vec<u16>> {
-            // On non-Windows platforms the best we can do to transform Vec<u16> from/to OS strings is to do the
-            // intermediate transformation into strings. Which invalidates non-utf8 paths that are actually
-            // valid.
-            os_str
-                .to_str()
-                .map(|s| s.encode_utf16().collect())
-                .ok_or_else(|| err_unsup_format!("{:?} is not a valid utf-8 string", os_str).into())
-        }
-
         let u16_vec = os_str_to_u16vec(os_str)?;
         // If `size` is smaller or equal than `bytes.len()`, writing `bytes` plus the required
         // 0x0000 terminator to memory would cause an out-of-bounds access.
@@ -187,6 +499,24 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         Ok((true, string_length))
     }
 
+    /// Helper function to write a block of 0x0000-terminated sequences of `u16`, terminated by
+    /// an additional 0x0000 (the same layout `read_os_str_block_from_wide_str` reads). Follows
+    /// the same `(bool, u64)` size-check convention as `write_os_str_to_wide_str`: the returned
+    /// length is the combined length of all strings, not counting any of the null terminators.
+    fn write_os_str_block_to_wide_str(
+        &mut self,
+        os_strings: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        scalar: Scalar<Tag>,
+        size: u64,
+    ) -> InterpResult<'tcx, (bool, u64)> {
+        let (block, string_length) = encode_wide_str_block(os_strings)?;
+        if !wide_str_block_fits(&block, size) {
+            return Ok((false, string_length));
+        }
+        self.eval_context_mut().memory.write_u16s(scalar, block)?;
+        Ok((true, string_length))
+    }
+
     /// Allocate enough memory to store the given `OsStr` as a null-terminated sequence of bytes.
     fn alloc_os_str_as_c_str(
         &mut self,
@@ -226,7 +556,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         let this = self.eval_context_ref();
         let os_str = this.read_os_str_from_c_str(scalar)?;
 
-        Ok(match convert_path_separator(Cow::Borrowed(os_str), &this.tcx.sess.target.target.target_os, Pathconversion::TargetToHost) {
+        Ok(match convert_path_separator(os_str, &this.tcx.sess.target.target.target_os, Pathconversion::TargetToHost) {
             Cow::Borrowed(x) => Cow::Borrowed(Path::new(x)),
             Cow::Owned(y) => Cow::Owned(PathBuf::from(y)),
         })
@@ -266,3 +596,103 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         this.write_os_str_to_wide_str(&os_str, scalar, size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wtf8_round_trips_unpaired_surrogates() {
+        let units: Vec<u16> = vec![0x0041, 0xD800, 0x0042, 0xDFFF, 0x0043];
+        let bytes = wtf8::encode_wide(&units);
+        assert_eq!(wtf8::decode_wide(&bytes), units);
+    }
+
+    #[test]
+    fn wtf8_round_trips_surrogate_pairs() {
+        // U+1F600 GRINNING FACE, as a high/low surrogate pair.
+        let units: Vec<u16> = vec![0xD83D, 0xDE00];
+        let bytes = wtf8::encode_wide(&units);
+        assert_eq!(bytes, "😀".as_bytes());
+        assert_eq!(wtf8::decode_wide(&bytes), units);
+    }
+
+    #[test]
+    fn wtf8_round_trips_non_utf8_bytes() {
+        // A lone continuation byte, an overlong-style lead byte, and a truncated sequence all
+        // fail to decode as UTF-8, but must still come back byte-for-byte.
+        for bytes in [&b"foo\xffbar"[..], &b"\x80"[..], &b"\xc0\x80"[..], &b"\xe0\x80"[..]] {
+            let units = wtf8::decode_wide(bytes);
+            assert_eq!(wtf8::encode_wide(&units), bytes);
+        }
+    }
+
+    #[test]
+    fn split_drive_prefix_handles_verbatim_and_bare_forms() {
+        assert_eq!(split_windows_drive_prefix(br"C:\foo\bar"), (Some(b'C'), &br"\foo\bar"[..]));
+        assert_eq!(split_windows_drive_prefix(br"\\?\C:\foo"), (Some(b'C'), &br"\foo"[..]));
+        assert_eq!(split_windows_drive_prefix(b"c:foo"), (Some(b'C'), &b"foo"[..]));
+        assert_eq!(split_windows_drive_prefix(b"relative\\path"), (None, &b"relative\\path"[..]));
+    }
+
+    #[test]
+    fn host_root_table_lookup_is_data_driven() {
+        assert_eq!(host_root_for_drive(b'C'), Some("/"));
+        assert_eq!(host_root_for_drive(b'D'), None);
+        assert_eq!(drive_for_host_path(b"/tmp/foo"), Some((b'C', "/")));
+        assert_eq!(drive_for_host_path(b"nope"), None);
+    }
+
+    #[test]
+    fn wide_str_block_round_trips() {
+        // No empty string in the list: like `GetEnvironmentStringsW`'s block, an empty entry
+        // is indistinguishable from the block's own trailing terminator.
+        let strings = vec![OsString::from("foo"), OsString::from("bar")];
+        let (block, string_length) = encode_wide_str_block(&strings).unwrap();
+        // "foo\0bar\0\0": each string's own terminator, plus one more for the block.
+        assert_eq!(block, [0x66, 0x6F, 0x6F, 0, 0x62, 0x61, 0x72, 0, 0]);
+        assert_eq!(string_length, 6);
+        assert_eq!(decode_wide_str_block(&block).unwrap(), strings);
+    }
+
+    #[test]
+    fn wide_str_block_rejects_undersized_destination() {
+        let strings = vec![OsString::from("foo"), OsString::from("bar")];
+        let (block, string_length) = encode_wide_str_block(&strings).unwrap();
+        let required_size = u64::try_from(block.len()).unwrap();
+        assert_eq!(required_size, 9); // "foo\0bar\0\0"
+        assert_eq!(string_length, 6);
+        assert!(!wide_str_block_fits(&block, required_size - 1));
+        assert!(wide_str_block_fits(&block, required_size));
+        assert!(wide_str_block_fits(&block, required_size + 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn convert_path_separator_target_to_host_joins_drive_with_one_separator() {
+        let os_str = OsStr::from_bytes(br"C:\tmp\foo");
+        let converted =
+            convert_path_separator(Cow::Borrowed(os_str), "windows", Pathconversion::TargetToHost);
+        assert_eq!(converted.as_bytes(), b"/tmp/foo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn convert_path_separator_host_to_target_joins_drive_with_one_separator() {
+        let os_str = OsStr::from_bytes(b"/tmp/foo");
+        let converted =
+            convert_path_separator(Cow::Borrowed(os_str), "windows", Pathconversion::HostToTarget);
+        assert_eq!(converted.as_bytes(), br"C:\tmp\foo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn convert_path_separator_target_to_host_keeps_driveless_absolute_path() {
+        // No `C:` prefix here, so the leading separator is part of the path itself and must
+        // survive, not be mistaken for (and stripped like) the one following a drive letter.
+        let os_str = OsStr::from_bytes(br"\foo\bar");
+        let converted =
+            convert_path_separator(Cow::Borrowed(os_str), "windows", Pathconversion::TargetToHost);
+        assert_eq!(converted.as_bytes(), b"/foo/bar");
+    }
+}